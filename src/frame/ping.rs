@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use crate::frame::{Error, Frame, Head, Kind, StreamId};
 use bytes::BufMut;
 
@@ -15,13 +17,36 @@ pub struct Ping {
 // zeroes to distinguish this specific PING from any other.
 const SHUTDOWN_PAYLOAD: Payload = [0x0b, 0x7b, 0xa2, 0xf0, 0x8b, 0x9b, 0xfe, 0x54];
 
+// The first 7 bytes identify a keepalive ping sent by `KeepAlive` after the
+// connection has been idle for too long, distinct from `SHUTDOWN_PAYLOAD`
+// and the user-ping prefix. The last byte is a per-probe nonce so a PONG
+// can be matched against the specific probe that is still outstanding,
+// rather than any keepalive probe ever sent.
+const KEEPALIVE_PREFIX: [u8; 7] = [0x4b, 0x65, 0x65, 0x70, 0x41, 0x6c, 0x76];
+
 macro_rules! user_payload {
     ($id:expr) => {
         [0x3b, 0x7c, 0xdb, 0x7a, 0x0b, 0x87, 0x16, $id]
     };
 }
 
-const USER_PAYLOADS: [Payload; 2] = [user_payload![0], user_payload![1]];
+// The first 7 bytes identify the payload as belonging to the user-ping id
+// space; the last byte is a wrapping counter handed out by `PingPong` so
+// that many user pings can be outstanding at once without colliding.
+const USER_PAYLOAD_PREFIX: [u8; 7] = [0x3b, 0x7c, 0xdb, 0x7a, 0x0b, 0x87, 0x16];
+
+// Tags a payload as carrying a measurement timestamp in its remaining 7
+// bytes, used by both RTT sampling (`rtt::Estimator`) and BDP probing
+// (`bdp::Estimator`). The tag only needs to occupy the first byte, since
+// the remaining 7 bytes are free to vary with the timestamp; distinct
+// tags keep the two kinds of measurement ping from being confused with
+// each other.
+const RTT_MEASUREMENT_TAG: u8 = 0xa5;
+const BDP_MEASUREMENT_TAG: u8 = 0xb6;
+
+// Both measurement pings truncate their timestamp to the low 56 bits, since
+// the tag byte occupies the rest of the payload.
+pub(crate) const TIMESTAMP_MASK: u64 = 0x00ff_ffff_ffff_ffff;
 
 impl Ping {
     #[cfg(feature = "unstable")]
@@ -30,12 +55,6 @@ impl Ping {
     #[cfg(not(feature = "unstable"))]
     pub(crate) const SHUTDOWN: Payload = SHUTDOWN_PAYLOAD;
 
-    #[cfg(feature = "unstable")]
-    pub const USERS: [Payload; 2] = USER_PAYLOADS;
-
-    #[cfg(not(feature = "unstable"))]
-    pub(crate) const USERS: [Payload; 2] = USER_PAYLOADS;
-
     pub fn new(payload: Payload) -> Ping {
         Ping {
             ack: false,
@@ -59,14 +78,74 @@ impl Ping {
         self.payload
     }
 
+    /// Builds the payload for a user ping tagged with `id`, the wrapping
+    /// counter value assigned to it by `PingPong`.
+    pub(crate) fn user_payload(id: u8) -> Payload {
+        user_payload![id]
+    }
+
     pub(crate) fn user_payload_id(&self) -> Option<u8> {
-        if self.payload[0..7] == USER_PAYLOADS[0][0..7] {
+        if self.payload[0..7] == USER_PAYLOAD_PREFIX {
             Some(self.payload[7])
         } else {
             None
         }
     }
 
+    /// Builds the payload for a keepalive probe tagged with `nonce`, so its
+    /// PONG can be matched against this specific probe.
+    pub(crate) fn keepalive_payload(nonce: u8) -> Payload {
+        let mut payload = [0; 8];
+        payload[0..7].copy_from_slice(&KEEPALIVE_PREFIX);
+        payload[7] = nonce;
+        payload
+    }
+
+    /// If this ping (or its echoed pong) is a keepalive probe, returns its
+    /// nonce.
+    pub(crate) fn keepalive_nonce(&self) -> Option<u8> {
+        if self.payload[0..7] == KEEPALIVE_PREFIX {
+            Some(self.payload[7])
+        } else {
+            None
+        }
+    }
+
+    /// Builds the payload for an RTT-measurement ping, encoding `nanos`
+    /// (truncated to 56 bits) alongside the reserved tag byte.
+    pub(crate) fn rtt_payload(nanos: u64) -> Payload {
+        tagged_timestamp_payload(RTT_MEASUREMENT_TAG, nanos)
+    }
+
+    /// If this ping (or its echoed pong) carries an RTT-measurement
+    /// timestamp, returns it.
+    pub(crate) fn rtt_timestamp(&self) -> Option<u64> {
+        untag_timestamp_payload(RTT_MEASUREMENT_TAG, &self.payload)
+    }
+
+    /// Builds the payload for a BDP probe ping, encoding `nanos`
+    /// (truncated to 56 bits) alongside the reserved tag byte.
+    pub(crate) fn bdp_payload(nanos: u64) -> Payload {
+        tagged_timestamp_payload(BDP_MEASUREMENT_TAG, nanos)
+    }
+
+    /// If this ping (or its echoed pong) carries a BDP-probe timestamp,
+    /// returns it.
+    pub(crate) fn bdp_timestamp(&self) -> Option<u64> {
+        untag_timestamp_payload(BDP_MEASUREMENT_TAG, &self.payload)
+    }
+
+    /// Monotonic nanosecond timestamp since `start`, truncated to the
+    /// same 56 bits that fit in a measurement payload.
+    ///
+    /// Shared by `rtt::Estimator` and `bdp::Estimator` so both truncate
+    /// identically; an RTT sample is the wrapping difference of two
+    /// values produced by this function, masked back into the 56-bit
+    /// space to stay correct across a rollover.
+    pub(crate) fn elapsed_nanos(start: Instant) -> u64 {
+        (Instant::now() - start).as_nanos() as u64 & TIMESTAMP_MASK
+    }
+
     /// Builds a `Ping` frame from a raw frame.
     pub fn load(head: Head, bytes: &[u8]) -> Result<Ping, Error> {
         debug_assert_eq!(head.kind(), crate::frame::Kind::Ping);
@@ -115,3 +194,18 @@ impl<T> From<Ping> for Frame<T> {
         Frame::Ping(src)
     }
 }
+
+fn tagged_timestamp_payload(tag: u8, nanos: u64) -> Payload {
+    let be = nanos.to_be_bytes();
+    [tag, be[1], be[2], be[3], be[4], be[5], be[6], be[7]]
+}
+
+fn untag_timestamp_payload(tag: u8, payload: &Payload) -> Option<u64> {
+    if payload[0] != tag {
+        return None;
+    }
+
+    let mut be = [0; 8];
+    be[1..].copy_from_slice(&payload[1..]);
+    Some(u64::from_be_bytes(be))
+}
@@ -0,0 +1,187 @@
+use std::time::Instant;
+
+use crate::frame::Ping;
+
+/// Samples the bandwidth-delay product of the connection and grows the
+/// connection-level receive window to match, the same way QUIC and gRPC
+/// stacks size their receive buffers.
+///
+/// While data is flowing, a single BDP probe PING is sent and the number
+/// of connection-level bytes received is snapshotted. When the matching
+/// PONG returns, the elapsed time is the RTT and the bytes received since
+/// the snapshot is one BDP sample. If the largest sample seen so far
+/// (samples are never aged out) reaches roughly two-thirds of the current
+/// target window, the window is doubled (up to `max_window`) and a
+/// `WINDOW_UPDATE` is emitted.
+///
+/// This is opt-in: connections that don't enable it never send a probe
+/// and the target window never changes.
+#[derive(Debug)]
+pub(crate) struct Estimator {
+    enabled: bool,
+    start: Instant,
+    target_window: u32,
+    max_window: u32,
+    max_sample: u64,
+    outstanding: Option<Outstanding>,
+}
+
+#[derive(Debug)]
+struct Outstanding {
+    sent_nanos: u64,
+    bytes_received_at_probe: u64,
+}
+
+/// Two thirds, expressed as a fraction to avoid floating point.
+const GROW_THRESHOLD_NUM: u64 = 2;
+const GROW_THRESHOLD_DEN: u64 = 3;
+
+impl Estimator {
+    pub(crate) fn disabled() -> Self {
+        Estimator {
+            enabled: false,
+            start: Instant::now(),
+            target_window: 0,
+            max_window: 0,
+            max_sample: 0,
+            outstanding: None,
+        }
+    }
+
+    pub(crate) fn new(initial_window: u32, max_window: u32) -> Self {
+        Estimator {
+            enabled: true,
+            start: Instant::now(),
+            target_window: initial_window,
+            max_window,
+            max_sample: 0,
+            outstanding: None,
+        }
+    }
+
+    pub(crate) fn target_window(&self) -> u32 {
+        self.target_window
+    }
+
+    /// Called when data begins arriving on a connection that was
+    /// previously idle. Starts a probe if one isn't already outstanding;
+    /// sampling while idle would only measure the gap between bursts, not
+    /// the bandwidth-delay product.
+    pub(crate) fn on_data_flowing(&mut self, bytes_received_so_far: u64) -> Option<Ping> {
+        if !self.enabled || self.outstanding.is_some() {
+            return None;
+        }
+
+        let sent_nanos = self.now_nanos();
+        self.outstanding = Some(Outstanding {
+            sent_nanos,
+            bytes_received_at_probe: bytes_received_so_far,
+        });
+        Some(Ping::new(Ping::bdp_payload(sent_nanos)))
+    }
+
+    /// Handles an inbound PONG, completing the outstanding probe and
+    /// returning the new target window if it grew.
+    pub(crate) fn on_pong(&mut self, echoed_nanos: u64, bytes_received_now: u64) -> Option<u32> {
+        // Compare before taking: a stray/mismatched echo must leave the
+        // still-outstanding probe in place, or its real pong would later
+        // find `outstanding` empty and the sample would be lost.
+        match &self.outstanding {
+            Some(probe) if probe.sent_nanos == echoed_nanos => {}
+            _ => return None,
+        }
+        let probe = self.outstanding.take()?;
+
+        let bdp_sample = bytes_received_now.saturating_sub(probe.bytes_received_at_probe);
+        self.max_sample = self.max_sample.max(bdp_sample);
+
+        let threshold = (self.target_window as u64 * GROW_THRESHOLD_NUM) / GROW_THRESHOLD_DEN;
+        if self.max_sample >= threshold {
+            let doubled = self.target_window.saturating_mul(2).min(self.max_window);
+            if doubled > self.target_window {
+                self.target_window = doubled;
+                return Some(self.target_window);
+            }
+        }
+
+        None
+    }
+
+    fn now_nanos(&self) -> u64 {
+        Ping::elapsed_nanos(self.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_estimator_never_probes() {
+        let mut est = Estimator::disabled();
+        assert!(est.on_data_flowing(0).is_none());
+    }
+
+    #[test]
+    fn grows_window_once_sample_crosses_threshold() {
+        let mut est = Estimator::new(100, 1000);
+
+        let ping = est.on_data_flowing(0).expect("probe sent");
+        let sent = ping.bdp_timestamp().expect("bdp payload");
+
+        // Below the two-thirds threshold: no growth.
+        assert_eq!(est.on_pong(sent, 50), None);
+        assert_eq!(est.target_window(), 100);
+
+        let ping = est.on_data_flowing(50).expect("probe sent");
+        let sent = ping.bdp_timestamp().expect("bdp payload");
+
+        // At/above the two-thirds threshold: window doubles.
+        assert_eq!(est.on_pong(sent, 50 + 70), Some(200));
+        assert_eq!(est.target_window(), 200);
+    }
+
+    #[test]
+    fn window_growth_is_capped_at_max_window() {
+        let mut est = Estimator::new(100, 150);
+
+        let ping = est.on_data_flowing(0).expect("probe sent");
+        let sent = ping.bdp_timestamp().expect("bdp payload");
+
+        // Would double to 200, but max_window caps it at 150.
+        assert_eq!(est.on_pong(sent, 100), Some(150));
+        assert_eq!(est.target_window(), 150);
+    }
+
+    #[test]
+    fn only_one_probe_outstanding_at_a_time() {
+        let mut est = Estimator::new(100, 1000);
+
+        assert!(est.on_data_flowing(0).is_some());
+        assert!(est.on_data_flowing(10).is_none());
+    }
+
+    #[test]
+    fn mismatched_echo_is_ignored() {
+        let mut est = Estimator::new(100, 1000);
+        est.on_data_flowing(0);
+
+        assert_eq!(est.on_pong(0xdead_beef, 1000), None);
+        assert_eq!(est.target_window(), 100);
+    }
+
+    #[test]
+    fn mismatched_echo_does_not_eat_the_outstanding_probe() {
+        let mut est = Estimator::new(100, 1000);
+        let ping = est.on_data_flowing(0).expect("probe sent");
+        let sent = ping.bdp_timestamp().expect("bdp payload");
+
+        // A stray echo for some other ping must not consume the probe
+        // that's still genuinely outstanding.
+        assert_eq!(est.on_pong(0xdead_beef, 1000), None);
+
+        // The real pong for that probe must still resolve.
+        assert_eq!(est.on_pong(sent, 70), Some(200));
+        assert_eq!(est.target_window(), 200);
+    }
+}
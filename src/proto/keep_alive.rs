@@ -0,0 +1,254 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::time::Sleep;
+
+use crate::frame::Ping;
+
+/// Configuration for connection keepalive, passed to `KeepAlive::new`.
+/// No client or server builder option sets it.
+#[derive(Clone, Debug)]
+pub(crate) struct Config {
+    /// How long the connection may sit idle before a keepalive PING is
+    /// sent. `None` disables keepalive entirely.
+    pub interval: Option<Duration>,
+    /// How long to wait for the PONG before declaring the peer dead.
+    pub timeout: Duration,
+    /// Whether to keep probing while the connection is idle, or only
+    /// while streams are open.
+    pub while_idle: bool,
+}
+
+/// The last liveness outcome observed for a connection, so that callers
+/// can distinguish a clean shutdown from one forced by a dead peer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Liveness {
+    /// No keepalive probe has timed out (yet).
+    Alive,
+    /// A keepalive PING was sent and no matching PONG arrived before the
+    /// timeout elapsed.
+    TimedOut,
+}
+
+#[derive(Debug)]
+enum State {
+    /// Not currently counting down to a keepalive ping.
+    Init,
+    /// Waiting out the idle `interval` before sending a ping.
+    Scheduled(Pin<Box<Sleep>>),
+    /// A keepalive PING tagged with this nonce has been sent; waiting out
+    /// the `timeout` for its matching PONG, or for the connection to be
+    /// torn down.
+    PingSent(Pin<Box<Sleep>>, u8),
+    /// The timeout fired with no PONG; the connection is being closed.
+    Shutdown,
+}
+
+/// Drives the idle timer, sends keepalive PINGs, and declares the peer
+/// dead if the matching PONG doesn't arrive in time.
+///
+/// Only a single keepalive ping is ever outstanding: `on_frame` resets the
+/// idle timer on any inbound frame, and `while_idle = false` suppresses
+/// probing while the connection is idle. Each probe is
+/// tagged with a wrapping nonce, and `on_pong` only accepts a pong whose
+/// nonce matches the probe this instance is currently waiting on — a late
+/// pong for an earlier, already-abandoned probe must not be mistaken for
+/// confirmation of the current one, or a genuinely dead peer would go
+/// undetected.
+#[derive(Debug)]
+pub(crate) struct KeepAlive {
+    config: Config,
+    state: State,
+    liveness: Liveness,
+    next_nonce: u8,
+}
+
+impl KeepAlive {
+    pub(crate) fn new(config: Config) -> Self {
+        KeepAlive {
+            config,
+            state: State::Init,
+            liveness: Liveness::Alive,
+            next_nonce: 0,
+        }
+    }
+
+    pub(crate) fn liveness(&self) -> Liveness {
+        self.liveness
+    }
+
+    /// Called whenever any frame is read off the socket. Any inbound
+    /// activity resets the idle countdown, since it proves the peer is
+    /// still there.
+    pub(crate) fn on_frame(&mut self) {
+        if let Some(interval) = self.config.interval {
+            if !matches!(self.state, State::Shutdown) {
+                self.state = State::Scheduled(Box::pin(tokio::time::sleep(interval)));
+            }
+        }
+    }
+
+    /// Polls the keepalive timers, returning the PING to send when the
+    /// idle interval elapses, and `KeepAliveEvent::Expired` once the PONG
+    /// timeout fires without a response.
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>, is_idle: bool) -> Poll<KeepAliveEvent> {
+        let interval = match self.config.interval {
+            Some(interval) => interval,
+            None => return Poll::Pending,
+        };
+
+        if is_idle && !self.config.while_idle {
+            return Poll::Pending;
+        }
+
+        loop {
+            match &mut self.state {
+                State::Init => {
+                    self.state = State::Scheduled(Box::pin(tokio::time::sleep(interval)));
+                }
+                State::Scheduled(sleep) => {
+                    if sleep.as_mut().poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    let nonce = self.next_nonce;
+                    self.next_nonce = self.next_nonce.wrapping_add(1);
+                    self.state = State::PingSent(
+                        Box::pin(tokio::time::sleep(self.config.timeout)),
+                        nonce,
+                    );
+                    return Poll::Ready(KeepAliveEvent::SendPing(Ping::new(
+                        Ping::keepalive_payload(nonce),
+                    )));
+                }
+                State::PingSent(sleep, _) => {
+                    if sleep.as_mut().poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    self.liveness = Liveness::TimedOut;
+                    self.state = State::Shutdown;
+                    return Poll::Ready(KeepAliveEvent::Expired);
+                }
+                State::Shutdown => return Poll::Pending,
+            }
+        }
+    }
+
+    /// Called when an inbound PONG carries a keepalive nonce. Only resets
+    /// the timeout if `nonce` matches the probe currently outstanding; a
+    /// stale pong for an earlier probe (e.g. one abandoned when `on_frame`
+    /// reset the idle timer before its timeout fired) is ignored rather
+    /// than being mistaken for confirmation of the current probe.
+    pub(crate) fn on_pong(&mut self, nonce: u8) {
+        if matches!(&self.state, State::PingSent(_, pending) if *pending == nonce) {
+            self.state = State::Init;
+        }
+    }
+}
+
+/// An event produced by polling a [`KeepAlive`].
+#[derive(Debug)]
+pub(crate) enum KeepAliveEvent {
+    /// The idle interval elapsed; send this PING and start the timeout.
+    SendPing(Ping),
+    /// The timeout elapsed with no PONG; the connection should be closed
+    /// with a GOAWAY.
+    Expired,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::task::{RawWaker, RawWakerVTable, Waker};
+
+    fn noop_waker() -> Waker {
+        fn no_op(_: *const ()) {}
+        fn clone(_: *const ()) -> RawWaker {
+            raw_waker()
+        }
+        fn raw_waker() -> RawWaker {
+            static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, no_op, no_op, no_op);
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        unsafe { Waker::from_raw(raw_waker()) }
+    }
+
+    fn config() -> Config {
+        Config {
+            interval: Some(Duration::from_secs(10)),
+            timeout: Duration::from_secs(5),
+            while_idle: true,
+        }
+    }
+
+    fn send_ping_nonce(ka: &mut KeepAlive, cx: &mut Context<'_>) -> u8 {
+        match ka.poll(cx, false) {
+            Poll::Ready(KeepAliveEvent::SendPing(ping)) => {
+                ping.keepalive_nonce().expect("keepalive payload")
+            }
+            other => panic!("expected a keepalive probe, got {:?}", other),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stale_pong_does_not_confirm_a_newer_probe() {
+        let mut ka = KeepAlive::new(config());
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        // Arms the idle timer.
+        assert!(ka.poll(&mut cx, false).is_pending());
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        let first = send_ping_nonce(&mut ka, &mut cx);
+
+        // An unrelated frame arrives before the first probe's pong,
+        // abandoning it and restarting the idle countdown.
+        ka.on_frame();
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        let second = send_ping_nonce(&mut ka, &mut cx);
+        assert_ne!(first, second);
+
+        // The first probe's pong finally arrives, late. It must not be
+        // mistaken for confirmation of the second, still-outstanding probe.
+        ka.on_pong(first);
+        assert!(matches!(&ka.state, State::PingSent(_, nonce) if *nonce == second));
+        assert_eq!(ka.liveness(), Liveness::Alive);
+
+        // Only the matching nonce resets the timeout.
+        ka.on_pong(second);
+        assert!(matches!(ka.state, State::Init));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn matching_pong_resets_before_timeout() {
+        let mut ka = KeepAlive::new(config());
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(ka.poll(&mut cx, false).is_pending());
+        tokio::time::advance(Duration::from_secs(10)).await;
+        let nonce = send_ping_nonce(&mut ka, &mut cx);
+
+        ka.on_pong(nonce);
+        assert!(matches!(ka.state, State::Init));
+        assert_eq!(ka.liveness(), Liveness::Alive);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unanswered_probe_declares_peer_dead() {
+        let mut ka = KeepAlive::new(config());
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(ka.poll(&mut cx, false).is_pending());
+        tokio::time::advance(Duration::from_secs(10)).await;
+        send_ping_nonce(&mut ka, &mut cx);
+
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert!(matches!(ka.poll(&mut cx, false), Poll::Ready(KeepAliveEvent::Expired)));
+        assert_eq!(ka.liveness(), Liveness::TimedOut);
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::num::Wrapping;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::frame::Ping;
+use crate::Error;
+
+/// Registry of outstanding application-level pings, shared between the
+/// connection task that drives the socket and any `PingPong` handles
+/// obtained from it.
+///
+/// Each outstanding ping is keyed by the single id byte baked into its
+/// `Ping::user_payload`. That id space is only a `u8`, so at most 256
+/// pings may be outstanding at once; allocating an id that is still in
+/// use is taken to mean the previous ping using it was abandoned without
+/// ever receiving a pong: its waiter is replaced by the new one, and its
+/// future resolves with an error.
+#[derive(Clone, Debug)]
+pub(crate) struct UserPings {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    next_id: Wrapping<u8>,
+    outstanding: HashMap<u8, oneshot::Sender<Duration>>,
+}
+
+impl UserPings {
+    pub(crate) fn new() -> Self {
+        UserPings {
+            inner: Arc::new(Mutex::new(Inner::default())),
+        }
+    }
+
+    /// Allocates the next id in the wrapping counter and registers a
+    /// waiter for its pong, returning the id and the `Ping` frame to send.
+    fn register(&self) -> (u8, Ping, oneshot::Receiver<Duration>) {
+        let (tx, rx) = oneshot::channel();
+        let mut me = self.inner.lock().unwrap();
+
+        let id = me.next_id.0;
+        me.next_id += Wrapping(1);
+        me.outstanding.insert(id, tx);
+
+        (id, Ping::new(Ping::user_payload(id)), rx)
+    }
+
+    /// Releases `id` without it ever receiving a pong, e.g. because the
+    /// PING could never be sent. Dropping the sender wakes the waiting
+    /// receiver with an error instead of leaving it parked forever.
+    fn cancel(&self, id: u8) {
+        self.inner.lock().unwrap().outstanding.remove(&id);
+    }
+
+    /// Called by the connection task when a PONG carrying a user payload
+    /// id is received. Resolves the matching waiter, if it is still
+    /// outstanding.
+    pub(crate) fn receive_pong(&self, id: u8, rtt: Duration) {
+        if let Some(tx) = self.inner.lock().unwrap().outstanding.remove(&id) {
+            let _ = tx.send(rtt);
+        }
+    }
+}
+
+/// A handle for sending application-level PING frames and awaiting their
+/// round trip.
+///
+/// Built by `PingPong::new` from a `UserPings` registry and the sending
+/// half of a channel of outbound PINGs; no `Connection` or `SendStream`
+/// method hands one out. Each `ping` sends its PING on that channel, and
+/// its future resolves when `UserPings::receive_pong` is called with the
+/// ping's id. Cloning a `PingPong` is cheap, and any number of handles
+/// may have pings outstanding concurrently.
+#[derive(Clone, Debug)]
+pub struct PingPong {
+    user_pings: UserPings,
+    pings_tx: mpsc::UnboundedSender<Ping>,
+}
+
+impl PingPong {
+    pub(crate) fn new(user_pings: UserPings, pings_tx: mpsc::UnboundedSender<Ping>) -> Self {
+        PingPong {
+            user_pings,
+            pings_tx,
+        }
+    }
+
+    /// Sends a PING frame and returns a future that resolves to the
+    /// measured round-trip time once the peer's PONG arrives.
+    pub fn ping(&mut self) -> SendPing {
+        let (id, ping, rx) = self.user_pings.register();
+
+        let state = match self.pings_tx.send(ping) {
+            Ok(()) => SendPingState::Pending(rx),
+            Err(_) => {
+                // The connection task is gone and will never read this id
+                // off the channel, so it will never pong it either; free
+                // the slot immediately instead of waiting on wraparound.
+                self.user_pings.cancel(id);
+                SendPingState::Failed(Some(closed_error()))
+            }
+        };
+
+        SendPing { state }
+    }
+}
+
+/// Future returned by [`PingPong::ping`].
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct SendPing {
+    state: SendPingState,
+}
+
+#[derive(Debug)]
+enum SendPingState {
+    Failed(Option<Error>),
+    Pending(oneshot::Receiver<Duration>),
+}
+
+impl Future for SendPing {
+    type Output = Result<Duration, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let me = self.get_mut();
+        match &mut me.state {
+            SendPingState::Failed(e) => {
+                Poll::Ready(Err(e.take().expect("polled SendPing after completion")))
+            }
+            SendPingState::Pending(rx) => Pin::new(rx)
+                .poll(cx)
+                .map(|res| res.map_err(|_| closed_error())),
+        }
+    }
+}
+
+fn closed_error() -> Error {
+    Error::from(io::Error::from(io::ErrorKind::BrokenPipe))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_reuse_drops_old_waiter() {
+        let pings = UserPings::new();
+
+        let (id, _ping, mut rx_old) = pings.register();
+        {
+            // Force the next registration to collide with `id`, as if the
+            // wrapping counter had cycled all the way back around.
+            let mut inner = pings.inner.lock().unwrap();
+            inner.next_id = Wrapping(id);
+        }
+        let (id_new, _ping, mut rx_new) = pings.register();
+        assert_eq!(id, id_new);
+
+        // The old waiter's sender was overwritten, not merely shadowed: it
+        // observes the channel close rather than ever resolving.
+        assert_eq!(
+            rx_old.try_recv(),
+            Err(oneshot::error::TryRecvError::Closed)
+        );
+
+        pings.receive_pong(id, Duration::from_millis(5));
+        assert_eq!(rx_new.try_recv(), Ok(Duration::from_millis(5)));
+    }
+
+    #[test]
+    fn cancel_drops_the_waiter_and_is_idempotent() {
+        let pings = UserPings::new();
+
+        let (id, _ping, mut rx) = pings.register();
+        pings.cancel(id);
+
+        assert_eq!(
+            rx.try_recv(),
+            Err(oneshot::error::TryRecvError::Closed)
+        );
+
+        // A pong arriving after cancellation has nothing left to resolve.
+        pings.receive_pong(id, Duration::from_millis(5));
+
+        // Cancelling an id that is no longer outstanding is a no-op.
+        pings.cancel(id);
+    }
+}
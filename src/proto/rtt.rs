@@ -0,0 +1,149 @@
+use std::time::{Duration, Instant};
+
+use crate::frame::ping::TIMESTAMP_MASK;
+use crate::frame::Ping;
+
+/// Tracks round-trip time by timestamping outbound measurement PINGs and
+/// comparing against the echoed value on their PONG.
+///
+/// Follows the same smoothing used for TCP's RTO estimator (RFC 6298):
+/// `srtt` is an EWMA of observed samples and `rttvar` tracks the mean
+/// deviation, giving a stable estimate that still reacts to real
+/// latency shifts.
+#[derive(Debug)]
+pub(crate) struct Estimator {
+    start: Instant,
+    outstanding: Option<u64>,
+    srtt: Option<Duration>,
+    rttvar: Duration,
+}
+
+impl Estimator {
+    pub(crate) fn new() -> Self {
+        Estimator {
+            start: Instant::now(),
+            outstanding: None,
+            srtt: None,
+            rttvar: Duration::from_millis(0),
+        }
+    }
+
+    /// Builds a measurement PING timestamped against this estimator's
+    /// epoch, and remembers it as the one outstanding sample.
+    ///
+    /// Only one RTT sample may be outstanding at a time; starting a new
+    /// one discards the previous, unanswered one.
+    pub(crate) fn send_ping(&mut self) -> Ping {
+        let nanos = self.now_nanos();
+        self.outstanding = Some(nanos);
+        Ping::new(Ping::rtt_payload(nanos))
+    }
+
+    /// Handles an inbound PONG, completing the outstanding sample if the
+    /// echoed timestamp matches the one we sent.
+    ///
+    /// Echoed timestamps that don't match the outstanding sample are
+    /// ignored rather than erroring: they may belong to a probe this
+    /// estimator never sent, or to a sample that was already superseded.
+    pub(crate) fn on_pong(&mut self, echoed_nanos: u64) -> Option<Duration> {
+        // Compare before taking: a stale echo for an already-superseded
+        // probe must leave the still-outstanding sample in place, or its
+        // real pong would later find `outstanding` empty and be dropped.
+        if self.outstanding != Some(echoed_nanos) {
+            return None;
+        }
+        let sent = self.outstanding.take()?;
+
+        let sample = Duration::from_nanos(elapsed_since(sent, self.now_nanos()));
+        self.update(sample);
+        self.srtt
+    }
+
+    pub(crate) fn rtt(&self) -> Option<Duration> {
+        self.srtt
+    }
+
+    fn update(&mut self, sample: Duration) {
+        match self.srtt {
+            None => {
+                self.srtt = Some(sample);
+                self.rttvar = sample / 2;
+            }
+            Some(srtt) => {
+                let diff = if sample > srtt {
+                    sample - srtt
+                } else {
+                    srtt - sample
+                };
+                self.rttvar = (self.rttvar * 3 + diff) / 4;
+                self.srtt = Some((srtt * 7 + sample) / 8);
+            }
+        }
+    }
+
+    fn now_nanos(&self) -> u64 {
+        Ping::elapsed_nanos(self.start)
+    }
+}
+
+// Both `sent` and `now` are already truncated to 56 bits by `elapsed_nanos`.
+// A plain 64-bit `wrapping_sub` would underflow to a bogus multi-century
+// value once `now` rolls past the 56-bit boundary and back to a small
+// number while `sent` is still near the top of the range, so the result
+// must be masked back into the same 56-bit space before it's a valid
+// duration.
+fn elapsed_since(sent: u64, now: u64) -> u64 {
+    now.wrapping_sub(sent) & TIMESTAMP_MASK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elapsed_since_handles_56_bit_rollover() {
+        let sent = TIMESTAMP_MASK - 0x10;
+        let now = 0x20; // wrapped around past the 56-bit boundary
+        assert_eq!(elapsed_since(sent, now), 0x30);
+    }
+
+    #[test]
+    fn elapsed_since_without_rollover() {
+        assert_eq!(elapsed_since(100, 150), 50);
+    }
+
+    #[test]
+    fn on_pong_rejects_mismatched_echo() {
+        let mut est = Estimator::new();
+        est.send_ping();
+
+        assert_eq!(est.on_pong(0xdead_beef), None);
+        assert_eq!(est.rtt(), None);
+    }
+
+    #[test]
+    fn on_pong_accepts_matching_echo() {
+        let mut est = Estimator::new();
+        let ping = est.send_ping();
+        let sent = ping.rtt_timestamp().expect("rtt payload");
+
+        assert!(est.on_pong(sent).is_some());
+        assert!(est.rtt().is_some());
+    }
+
+    #[test]
+    fn stale_echo_does_not_eat_the_current_sample() {
+        let mut est = Estimator::new();
+
+        let stale = est.send_ping().rtt_timestamp().expect("rtt payload");
+        let fresh = est.send_ping().rtt_timestamp().expect("rtt payload");
+        assert_ne!(stale, fresh);
+
+        // The old probe's echo arrives after it's been superseded.
+        assert_eq!(est.on_pong(stale), None);
+
+        // The real pong for the still-outstanding probe must still resolve.
+        assert!(est.on_pong(fresh).is_some());
+        assert!(est.rtt().is_some());
+    }
+}